@@ -0,0 +1,44 @@
+//! Benchmarks for parsing large, `pip-compile --generate-hashes`-style `requirements.txt` files,
+//! which are dominated by `\`-continued, multi-hash entries.
+use std::fmt::Write;
+use std::str::FromStr;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use puffin_requirements::Requirements;
+
+/// Build a synthetic `pip-compile --generate-hashes` output with `count` packages, each with
+/// `hashes_per_package` `--hash=sha256:...` entries, to approximate a large, real-world lock file.
+fn pip_compile_fixture(count: usize, hashes_per_package: usize) -> String {
+    let mut text = String::new();
+    for i in 0..count {
+        writeln!(text, "package-{i}==1.0.{i} \\").unwrap();
+        for h in 0..hashes_per_package {
+            let sep = if h + 1 == hashes_per_package {
+                ""
+            } else {
+                " \\"
+            };
+            writeln!(text, "    --hash=sha256:{i:032x}{h:032x}{sep}").unwrap();
+        }
+        writeln!(text, "    # via some-other-package").unwrap();
+    }
+    text
+}
+
+fn bench_pip_compile(c: &mut Criterion) {
+    let small = pip_compile_fixture(100, 2);
+    let large = pip_compile_fixture(2_000, 4);
+
+    let mut group = c.benchmark_group("parse_requirements");
+    group.bench_function("small", |b| {
+        b.iter(|| Requirements::from_str(&small).unwrap());
+    });
+    group.bench_function("large", |b| {
+        b.iter(|| Requirements::from_str(&large).unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_pip_compile);
+criterion_main!(benches);
@@ -1,175 +1,707 @@
 use std::borrow::Cow;
-use std::ops::Deref;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
 use std::str::FromStr;
 
 use anyhow::Result;
 use memchr::{memchr2, memchr_iter};
 use pep508_rs::{Pep508Error, Requirement};
 
-#[derive(Debug)]
-pub struct Requirements(Vec<Requirement>);
+mod resolver;
+
+pub use resolver::{resolve, ResolveError, ResolvedRequirements};
+
+/// The parsed contents of a `requirements.txt` file: versioned requirements, editable/URL
+/// installs, and global options such as `--index-url`.
+#[derive(Debug, Default)]
+pub struct Requirements {
+    requirements: Vec<RequirementEntry>,
+    editables: Vec<EditableRequirement>,
+    options: Vec<GlobalOption>,
+}
+
+impl Requirements {
+    /// The versioned PEP 508 requirements in the file.
+    pub fn requirements(&self) -> &[RequirementEntry] {
+        &self.requirements
+    }
+
+    /// The `-e`/`--editable` and direct URL requirements in the file.
+    pub fn editables(&self) -> &[EditableRequirement] {
+        &self.editables
+    }
+
+    /// The global options (e.g. `--index-url`, `--pre`) declared in the file.
+    pub fn options(&self) -> &[GlobalOption] {
+        &self.options
+    }
+
+    /// Parse `s` as a `requirements.txt` file, first expanding any `${NAME}` environment variable
+    /// references (e.g. in index URL credentials) using `env`.
+    pub fn from_str_with_env(
+        s: &str,
+        env: &HashMap<String, String>,
+        on_missing: MissingEnvVar,
+    ) -> Result<Self, RequirementsError> {
+        Self::collect(RequirementsIterator::with_env(s, env, on_missing))
+    }
+
+    fn collect(iter: RequirementsIterator<'_>) -> Result<Self, RequirementsError> {
+        let mut requirements = Self::default();
+
+        for line in iter {
+            match RequirementsEntry::parse(line?)? {
+                RequirementsEntry::Requirement(entry) => requirements.requirements.push(entry),
+                RequirementsEntry::Editable(entry) => requirements.editables.push(entry),
+                RequirementsEntry::GlobalOption(option) => requirements.options.push(option),
+            }
+        }
+
+        Ok(requirements)
+    }
+}
 
 impl FromStr for Requirements {
-    type Err = Pep508Error;
+    type Err = RequirementsError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self(
-            RequirementsIterator::new(s)
-                .map(|requirement| Requirement::from_str(requirement.as_str()))
-                .collect::<Result<Vec<Requirement>, Pep508Error>>()?,
-        ))
+        Self::collect(RequirementsIterator::new(s))
+    }
+}
+
+/// A single requirement, along with any `--hash` entries attached to it in the source file.
+#[derive(Debug)]
+pub struct RequirementEntry {
+    pub requirement: Requirement,
+    pub hashes: Vec<Hash>,
+}
+
+/// A single entry parsed from a `requirements.txt` line, after comments and `--hash` extras have
+/// been stripped.
+#[derive(Debug)]
+pub(crate) enum RequirementsEntry {
+    /// A versioned PEP 508 requirement.
+    Requirement(RequirementEntry),
+    /// A `-e`/`--editable` entry, or a direct `file://`/`https://` URL requirement.
+    Editable(EditableRequirement),
+    /// A global option, such as `--index-url`, that applies to the whole file.
+    GlobalOption(GlobalOption),
+}
+
+impl RequirementsEntry {
+    /// Classify a trivia-stripped requirement line as a requirement, an editable/URL install, or
+    /// a global option.
+    pub(crate) fn parse(line: RequirementLine<'_>) -> Result<Self, RequirementsError> {
+        let text = line.as_str().trim();
+
+        if let Some(requirement) = strip_flag(text, &["-e", "--editable"]) {
+            return Ok(Self::Editable(EditableRequirement {
+                editable: true,
+                requirement: requirement.to_string(),
+            }));
+        }
+
+        if let Some(option) = GlobalOption::parse(text) {
+            return Ok(Self::GlobalOption(option));
+        }
+
+        if is_url(text) {
+            return Ok(Self::Editable(EditableRequirement {
+                editable: false,
+                requirement: text.to_string(),
+            }));
+        }
+
+        let span = line.span();
+        let line_number = line.line_number();
+        let requirement = Requirement::from_str(text).map_err(|error| {
+            RequirementsError::Pep508(Pep508SpanError {
+                error,
+                span,
+                line: line_number,
+            })
+        })?;
+
+        Ok(Self::Requirement(RequirementEntry {
+            requirement,
+            hashes: line.into_hashes(),
+        }))
+    }
+}
+
+/// A `-e`/`--editable` entry, or a direct `file://`/`https://` URL requirement.
+#[derive(Debug)]
+pub struct EditableRequirement {
+    /// Whether the entry was declared with `-e`/`--editable`, as opposed to a bare URL.
+    pub editable: bool,
+    /// The local path or URL to install from.
+    pub requirement: String,
+}
+
+/// A global option declared in a `requirements.txt` file, applying to the file as a whole rather
+/// than to a single requirement.
+#[derive(Debug, Clone)]
+pub struct GlobalOption {
+    pub kind: GlobalOptionKind,
+    pub value: Option<String>,
+}
+
+impl GlobalOption {
+    /// The global options that take a value, in the order they're checked.
+    const VALUED: &'static [(&'static str, GlobalOptionKind)] = &[
+        ("--index-url", GlobalOptionKind::IndexUrl),
+        ("--extra-index-url", GlobalOptionKind::ExtraIndexUrl),
+        ("--find-links", GlobalOptionKind::FindLinks),
+        ("--no-binary", GlobalOptionKind::NoBinary),
+    ];
+
+    /// Parse a single global option out of a trivia-stripped line, if it looks like one.
+    fn parse(text: &str) -> Option<Self> {
+        for (flag, kind) in Self::VALUED {
+            if let Some(value) = strip_flag(text, &[flag]) {
+                return Some(Self {
+                    kind: *kind,
+                    value: Some(value.to_string()),
+                });
+            }
+        }
+
+        if text == "--pre" {
+            return Some(Self {
+                kind: GlobalOptionKind::Pre,
+                value: None,
+            });
+        }
+
+        None
     }
 }
 
-impl Deref for Requirements {
-    type Target = [Requirement];
+/// The kind of a [`GlobalOption`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlobalOptionKind {
+    IndexUrl,
+    ExtraIndexUrl,
+    FindLinks,
+    NoBinary,
+    Pre,
+}
+
+/// Returns `true` if `text` looks like a direct URL requirement (e.g. `file://` or `https://`),
+/// as opposed to a PEP 508 specifier.
+fn is_url(text: &str) -> bool {
+    [
+        "file://", "http://", "https://", "git+", "hg+", "bzr+", "svn+",
+    ]
+    .iter()
+    .any(|scheme| text.starts_with(scheme))
+}
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+/// If `line` starts with one of `flags` followed by its argument (either `--flag value` or
+/// `--flag=value`), return the argument. Returns `None` for lookalikes, like `--requirements`
+/// when checking for `--requirement`, that share a prefix but aren't actually followed by a separator.
+pub(crate) fn strip_flag<'a>(line: &'a str, flags: &[&str]) -> Option<&'a str> {
+    for flag in flags {
+        let Some(rest) = line.strip_prefix(flag) else {
+            continue;
+        };
+        if let Some(value) = rest.strip_prefix('=') {
+            return Some(value.trim());
+        }
+        if rest.starts_with(char::is_whitespace) {
+            return Some(rest.trim_start());
+        }
     }
+    None
 }
 
+/// An error parsing a `requirements.txt` file.
 #[derive(Debug)]
-struct RequirementsIterator<'a> {
+pub enum RequirementsError {
+    /// The requirement itself failed to parse as a PEP 508 requirement.
+    Pep508(Pep508SpanError),
+    /// A `--hash` entry attached to a requirement was malformed.
+    Hash(HashParseError),
+    /// A `${NAME}` environment variable reference could not be expanded.
+    Env(EnvExpansionError),
+}
+
+impl fmt::Display for RequirementsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pep508(err) => write!(f, "{err}"),
+            Self::Hash(err) => write!(f, "{err}"),
+            Self::Env(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for RequirementsError {}
+
+impl From<HashParseError> for RequirementsError {
+    fn from(err: HashParseError) -> Self {
+        Self::Hash(err)
+    }
+}
+
+/// A [`Pep508Error`] annotated with the byte range and logical line number in the source
+/// `requirements.txt` file where it occurred, so that callers can point users at the exact line
+/// that failed to parse (including for `\`-continued multi-line entries).
+#[derive(Debug)]
+pub struct Pep508SpanError {
+    pub error: Pep508Error,
+    /// The byte range of the offending line (or multi-line entry) in the source text.
+    pub span: Range<usize>,
+    /// The 1-indexed logical line number at which the offending entry begins.
+    pub line: usize,
+}
+
+impl fmt::Display for Pep508SpanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {} (bytes {}..{}): {}",
+            self.line, self.span.start, self.span.end, self.error
+        )
+    }
+}
+
+impl std::error::Error for Pep508SpanError {}
+
+impl From<EnvExpansionError> for RequirementsError {
+    fn from(err: EnvExpansionError) -> Self {
+        Self::Env(err)
+    }
+}
+
+/// Controls how [`expand_env_vars`] handles a `${NAME}` reference whose variable isn't present in
+/// the supplied environment map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingEnvVar {
+    /// Leave the `${NAME}` placeholder untouched, mirroring pip's default behavior.
+    #[default]
+    Leave,
+    /// Fail with an [`EnvExpansionError`].
+    Error,
+}
+
+/// An error expanding a `${NAME}` environment variable reference in a requirement line.
+#[derive(Debug)]
+pub struct EnvExpansionError {
+    pub name: String,
+}
+
+impl fmt::Display for EnvExpansionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "environment variable `{}` is not set", self.name)
+    }
+}
+
+impl std::error::Error for EnvExpansionError {}
+
+/// Replace `${NAME}` references in `line` with values from `env`, per `on_missing`. Used to
+/// support pip's `${VAR}` substitution (commonly seen in index URL credentials) without requiring
+/// the caller to pre-process the file.
+fn expand_env_vars<'a>(
+    line: &'a str,
+    env: &HashMap<String, String>,
+    on_missing: MissingEnvVar,
+) -> Result<Cow<'a, str>, EnvExpansionError> {
+    if !line.contains("${") {
+        return Ok(Cow::Borrowed(line));
+    }
+
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + end];
+
+        output.push_str(&rest[..start]);
+        match env.get(name) {
+            Some(value) => output.push_str(value),
+            None => match on_missing {
+                MissingEnvVar::Leave => output.push_str(&rest[start..start + 2 + end + 1]),
+                MissingEnvVar::Error => {
+                    return Err(EnvExpansionError {
+                        name: name.to_string(),
+                    })
+                }
+            },
+        }
+
+        rest = &rest[start + 2 + end + 1..];
+    }
+    output.push_str(rest);
+
+    Ok(Cow::Owned(output))
+}
+
+/// A hash algorithm supported by `pip`'s `--hash` requirement option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = HashParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(Self::Sha256),
+            "sha384" => Ok(Self::Sha384),
+            "sha512" => Ok(Self::Sha512),
+            _ => Err(HashParseError::UnsupportedAlgorithm(s.to_string())),
+        }
+    }
+}
+
+impl HashAlgorithm {
+    /// The length, in hex characters, of a digest produced by this algorithm.
+    fn hex_len(self) -> usize {
+        match self {
+            Self::Sha256 => 64,
+            Self::Sha384 => 96,
+            Self::Sha512 => 128,
+        }
+    }
+}
+
+/// A single `--hash=<algorithm>:<digest>` entry, as emitted by `pip-compile --generate-hashes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hash {
+    pub algorithm: HashAlgorithm,
+    pub digest: String,
+}
+
+impl FromStr for Hash {
+    type Err = HashParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let token = s
+            .strip_prefix("--hash=")
+            .ok_or_else(|| HashParseError::MissingPrefix(s.to_string()))?;
+        let (algorithm, digest) = token
+            .split_once(':')
+            .ok_or_else(|| HashParseError::MissingDelimiter(s.to_string()))?;
+        if digest.is_empty() {
+            return Err(HashParseError::EmptyDigest(s.to_string()));
+        }
+        let algorithm = algorithm.parse::<HashAlgorithm>()?;
+        if digest.len() != algorithm.hex_len() || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(HashParseError::InvalidDigest(s.to_string()));
+        }
+        Ok(Self {
+            algorithm,
+            digest: digest.to_string(),
+        })
+    }
+}
+
+/// An error parsing a `--hash` entry out of a `requirements.txt` line.
+#[derive(Debug)]
+pub enum HashParseError {
+    /// The token didn't start with `--hash=`.
+    MissingPrefix(String),
+    /// The token was missing the `:` separating the algorithm from the digest.
+    MissingDelimiter(String),
+    /// The digest half of the token was empty.
+    EmptyDigest(String),
+    /// The digest wasn't valid hex, or wasn't the length expected for its algorithm.
+    InvalidDigest(String),
+    /// The algorithm wasn't one of the pip-supported hash algorithms.
+    UnsupportedAlgorithm(String),
+}
+
+impl fmt::Display for HashParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPrefix(s) => write!(f, "expected a `--hash=` entry, found: `{s}`"),
+            Self::MissingDelimiter(s) => {
+                write!(f, "expected `--hash=<algorithm>:<digest>`, found: `{s}`")
+            }
+            Self::EmptyDigest(s) => write!(f, "expected a non-empty digest, found: `{s}`"),
+            Self::InvalidDigest(s) => {
+                write!(
+                    f,
+                    "expected a hex digest of the expected length, found: `{s}`"
+                )
+            }
+            Self::UnsupportedAlgorithm(s) => write!(f, "unsupported hash algorithm: `{s}`"),
+        }
+    }
+}
+
+impl std::error::Error for HashParseError {}
+
+#[derive(Debug)]
+pub(crate) struct RequirementsIterator<'a> {
     text: &'a str,
     index: usize,
+    /// The 1-indexed logical line number of the next line to be read.
+    line_number: usize,
+    env: Option<&'a HashMap<String, String>>,
+    on_missing: MissingEnvVar,
 }
 
 impl<'a> RequirementsIterator<'a> {
-    fn new(text: &'a str) -> Self {
-        Self { text, index: 0 }
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            index: 0,
+            line_number: 1,
+            env: None,
+            on_missing: MissingEnvVar::default(),
+        }
+    }
+
+    /// Create an iterator that expands `${NAME}` environment variable references before parsing
+    /// each line.
+    pub(crate) fn with_env(
+        text: &'a str,
+        env: &'a HashMap<String, String>,
+        on_missing: MissingEnvVar,
+    ) -> Self {
+        Self {
+            text,
+            index: 0,
+            line_number: 1,
+            env: Some(env),
+            on_missing,
+        }
+    }
+
+    /// Expand `${NAME}` references in `line`, if this iterator was constructed with an
+    /// environment map.
+    fn expand(&self, line: Cow<'a, str>) -> Result<Cow<'a, str>, EnvExpansionError> {
+        let Some(env) = self.env else {
+            return Ok(line);
+        };
+        match line {
+            Cow::Borrowed(s) => expand_env_vars(s, env, self.on_missing),
+            Cow::Owned(s) => Ok(Cow::Owned(
+                expand_env_vars(&s, env, self.on_missing)?.into_owned(),
+            )),
+        }
+    }
+
+    /// Expand and parse a raw line into a [`RequirementLine`], which started at `line_number` and
+    /// spans `span` in the source text.
+    fn parse_line(
+        &self,
+        line: Cow<'a, str>,
+        span: Range<usize>,
+        line_number: usize,
+    ) -> Result<RequirementLine<'a>, RequirementsError> {
+        Ok(RequirementLine::from_line(
+            self.expand(line)?,
+            span,
+            line_number,
+        )?)
     }
 }
 
 #[derive(Debug)]
-struct RequirementLine<'a> {
+pub(crate) struct RequirementLine<'a> {
     /// The line as included in the `requirements.txt`, including comments and `--hash` extras.
     line: Cow<'a, str>,
     /// The line, with comments and `--hash` extras stripped.
     len: usize,
+    /// The `--hash` entries attached to the requirement, in source order.
+    hashes: Vec<Hash>,
+    /// The byte range of this (possibly multi-physical-line) entry in the source text.
+    span: Range<usize>,
+    /// The 1-indexed logical line number at which this entry begins.
+    line_number: usize,
 }
 
 impl<'a> RequirementLine<'a> {
-    /// Create a new `RequirementLine` from a line of text.
-    fn from_line(line: Cow<'a, str>) -> Self {
-        Self {
-            len: Self::strip_trivia(&line),
+    /// Create a new `RequirementLine` from a line of text, which has already had any `${NAME}`
+    /// environment variable references expanded.
+    fn from_line(
+        line: Cow<'a, str>,
+        span: Range<usize>,
+        line_number: usize,
+    ) -> Result<Self, HashParseError> {
+        let (len, hashes) = Self::strip_trivia(&line)?;
+        Ok(Self {
             line,
-        }
+            len,
+            hashes,
+            span,
+            line_number,
+        })
     }
 
     /// Return a parseable requirement line.
-    fn as_str(&self) -> &str {
+    pub(crate) fn as_str(&self) -> &str {
         &self.line[..self.len]
     }
 
+    /// Consume the line, returning the `--hash` entries attached to it.
+    pub(crate) fn into_hashes(self) -> Vec<Hash> {
+        self.hashes
+    }
+
+    /// The byte range of this entry in the source text.
+    pub(crate) fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// The 1-indexed logical line number at which this entry begins.
+    pub(crate) fn line_number(&self) -> usize {
+        self.line_number
+    }
+
     /// Strip trivia (comments and `--hash` extras) from a requirement, returning the length of the
-    /// requirement itself.
-    fn strip_trivia(requirement: &str) -> usize {
+    /// requirement itself along with any `--hash` entries that were attached to it.
+    fn strip_trivia(requirement: &str) -> Result<(usize, Vec<Hash>), HashParseError> {
+        let bytes = requirement.as_bytes();
         let mut len = requirement.len();
 
-        // Strip comments.
-        for position in memchr_iter(b'#', requirement[..len].as_bytes()) {
-            // The comment _must_ be preceded by whitespace.
-            if requirement[..len + position]
-                .chars()
-                .rev()
-                .next()
-                .is_some_and(char::is_whitespace)
-            {
+        // Strip comments. The `#` must be preceded by (ASCII) whitespace, or begin the line, to
+        // count: checking the raw byte avoids decoding a `char` just to look one position back.
+        for position in memchr_iter(b'#', &bytes[..len]) {
+            if position == 0 || bytes[position - 1].is_ascii_whitespace() {
                 len = position;
                 break;
             }
         }
 
-        // Strip `--hash` extras.
-        if let Some(index) = requirement[..len].find("--hash") {
+        // Strip and parse `--hash` extras.
+        let hashes = if let Some(index) = requirement[..len].find("--hash=") {
+            let hashes = requirement[index..len]
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<Hash>, HashParseError>>()?;
             len = index;
-        }
+            hashes
+        } else {
+            Vec::new()
+        };
 
-        len
+        Ok((len, hashes))
     }
 }
 
 impl<'a> Iterator for RequirementsIterator<'a> {
-    type Item = RequirementLine<'a>;
+    type Item = Result<RequirementLine<'a>, RequirementsError>;
 
     #[inline]
-    fn next(&mut self) -> Option<RequirementLine<'a>> {
-        if self.index == self.text.len() - 1 {
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.text.len() {
             return None;
         }
 
+        // The logical line we're about to read starts here.
+        let line_start = self.index;
+        let line_number = self.line_number;
+
         // Find the next line break.
         let Some((start, length)) = find_newline(&self.text[self.index..]) else {
             // Parse the rest of the text.
             let line = &self.text[self.index..];
-            self.index = self.text.len() - 1;
+            self.index = self.text.len();
 
-            // Skip fully-commented lines.
-            if line.trim_start().starts_with('#') {
+            // Skip fully-commented and empty lines.
+            if is_comment_line(line) || line.trim().is_empty() {
                 return None;
             }
 
-            // Skip empty lines.
-            if line.trim().is_empty() {
-                return None;
-            }
-
-            return Some(RequirementLine::from_line(Cow::Borrowed(line)));
+            return Some(self.parse_line(
+                Cow::Borrowed(line),
+                line_start..self.text.len(),
+                line_number,
+            ));
         };
 
         // Skip fully-commented lines.
-        if self.text[self.index..].trim_start().starts_with('#') {
+        if is_comment_line(&self.text[self.index..]) {
             self.index += start + length;
+            self.line_number += 1;
             return self.next();
         }
 
         // Skip empty lines.
         if self.text[self.index..self.index + start].trim().is_empty() {
             self.index += start + length;
+            self.line_number += 1;
             return self.next();
         }
 
         // If the newline is preceded by a continuation (\\), keep going.
-        if self.text[..self.index + start]
-            .chars()
-            .rev()
-            .next()
-            .is_some_and(|c| c == '\\')
-        {
-            // Add the line contents, preceding the continuation.
-            let mut line = self.text[self.index..self.index + start - 1].to_owned();
+        if ends_with_continuation(self.text.as_bytes(), self.index + start) {
+            // Continuations only ever remove bytes (the `\` and the newline that followed it),
+            // so the remaining text is an upper bound on the joined entry's length: size the
+            // buffer once up front rather than letting it grow as we stitch segments together.
+            let mut line = String::with_capacity(self.text.len() - self.index);
+            let mut span_end = self.index + start - 1;
+            line.push_str(&self.text[self.index..span_end]);
             self.index += start + length;
+            self.line_number += 1;
+
+            // Eat lines until we see a non-continuation, or run out of input.
+            loop {
+                let Some((start, length)) = find_newline(&self.text[self.index..]) else {
+                    // No trailing newline: the rest of the text is the final segment.
+                    span_end = self.text.len();
+                    line.push_str(&self.text[self.index..]);
+                    self.index = self.text.len();
+                    break;
+                };
 
-            // Eat lines until we see a non-continuation.
-            while let Some((start, length)) = find_newline(&self.text[self.index..]) {
-                if self.text[..self.index + start]
-                    .chars()
-                    .rev()
-                    .next()
-                    .is_some_and(|c| c == '\\')
-                {
+                if ends_with_continuation(self.text.as_bytes(), self.index + start) {
                     // Add the line contents, preceding the continuation.
-                    line.push_str(&self.text[self.index..self.index + start - 1]);
+                    span_end = self.index + start - 1;
+                    line.push_str(&self.text[self.index..span_end]);
                     self.index += start + length;
+                    self.line_number += 1;
                 } else {
-                    // Add the line contents, excluding the continuation.
-                    line.push_str(&self.text[self.index..self.index + start]);
+                    // Add the line contents, excluding the newline.
+                    span_end = self.index + start;
+                    line.push_str(&self.text[self.index..span_end]);
                     self.index += start + length;
+                    self.line_number += 1;
                     break;
                 }
             }
 
-            Some(RequirementLine::from_line(Cow::Owned(line)))
+            Some(self.parse_line(Cow::Owned(line), line_start..span_end, line_number))
         } else {
             let line = &self.text[self.index..self.index + start];
+            let span_end = self.index + start;
             self.index += start + length;
-            Some(RequirementLine::from_line(Cow::Borrowed(line)))
+            self.line_number += 1;
+            Some(self.parse_line(Cow::Borrowed(line), line_start..span_end, line_number))
         }
     }
 }
 
+/// Returns `true` if `text` starts a fully-commented line, i.e. a `#` preceded only by
+/// (ASCII) whitespace. `requirements.txt` files are ASCII, so scanning bytes instead of chars
+/// avoids UTF-8 decoding on this hot path.
+#[inline]
+fn is_comment_line(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    bytes.get(start) == Some(&b'#')
+}
+
+/// Returns `true` if the byte immediately before `end` is a `\` continuation marker.
+#[inline]
+fn ends_with_continuation(bytes: &[u8], end: usize) -> bool {
+    end > 0 && bytes[end - 1] == b'\\'
+}
+
 /// Return the start and end position of the first newline character in the given text.
 #[inline]
 fn find_newline(text: &str) -> Option<(usize, usize)> {
@@ -192,22 +724,159 @@ fn find_newline(text: &str) -> Option<(usize, usize)> {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::str::FromStr;
 
-    use insta::assert_debug_snapshot;
-
-    use crate::Requirements;
+    use crate::{
+        expand_env_vars, GlobalOptionKind, Hash, HashAlgorithm, HashParseError, MissingEnvVar,
+        Requirements, RequirementsEntry, RequirementsIterator,
+    };
     use anyhow::Result;
 
+    #[test]
+    fn expands_known_env_vars() {
+        let mut env = HashMap::new();
+        env.insert("TOKEN".to_string(), "secret".to_string());
+
+        let expanded =
+            expand_env_vars("https://${TOKEN}@host/simple", &env, MissingEnvVar::Leave).unwrap();
+
+        assert_eq!(expanded, "https://secret@host/simple");
+    }
+
+    #[test]
+    fn leaves_unset_var_untouched_by_default() {
+        let env = HashMap::new();
+
+        let expanded = expand_env_vars("${MISSING}/pkg", &env, MissingEnvVar::Leave).unwrap();
+
+        assert_eq!(expanded, "${MISSING}/pkg");
+    }
+
+    #[test]
+    fn errors_on_unset_var_when_configured() {
+        let env = HashMap::new();
+
+        let err = expand_env_vars("${MISSING}/pkg", &env, MissingEnvVar::Error).unwrap_err();
+
+        assert_eq!(err.name, "MISSING");
+    }
+
+    /// Parse a single line into a [`RequirementsEntry`], for exercising classification directly.
+    fn classify(line: &str) -> RequirementsEntry {
+        let mut iter = RequirementsIterator::new(line);
+        RequirementsEntry::parse(iter.next().unwrap().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn classifies_editable_requirement() {
+        match classify("-e ./local-pkg\n") {
+            RequirementsEntry::Editable(entry) => {
+                assert!(entry.editable);
+                assert_eq!(entry.requirement, "./local-pkg");
+            }
+            other => panic!("expected an editable requirement, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn classifies_direct_url_as_non_editable() {
+        match classify("https://example.com/pkg.whl\n") {
+            RequirementsEntry::Editable(entry) => {
+                assert!(!entry.editable);
+                assert_eq!(entry.requirement, "https://example.com/pkg.whl");
+            }
+            other => panic!("expected a direct URL requirement, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rejects_non_hex_digest() {
+        let err = "--hash=sha256:not-a-digest".parse::<Hash>().unwrap_err();
+        assert!(matches!(err, HashParseError::InvalidDigest(_)));
+    }
+
+    #[test]
+    fn rejects_digest_of_the_wrong_length() {
+        let err = "--hash=sha256:1234".parse::<Hash>().unwrap_err();
+        assert!(matches!(err, HashParseError::InvalidDigest(_)));
+    }
+
+    #[test]
+    fn classifies_global_option() {
+        match classify("--index-url https://example.com/simple\n") {
+            RequirementsEntry::GlobalOption(option) => {
+                assert_eq!(option.kind, GlobalOptionKind::IndexUrl);
+                assert_eq!(option.value.as_deref(), Some("https://example.com/simple"));
+            }
+            other => panic!("expected a global option, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn classifies_plain_pep508_requirement() {
+        match classify("flask==2.0\n") {
+            RequirementsEntry::Requirement(entry) => assert_eq!(&*entry.requirement.name, "flask"),
+            other => panic!("expected a requirement, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn pep508_error_reports_line_and_span_for_a_plain_line() {
+        let text = "flask==2.0\n!!!not-valid!!!\n";
+
+        match Requirements::from_str(text).unwrap_err() {
+            crate::RequirementsError::Pep508(err) => {
+                assert_eq!(err.line, 2);
+                assert_eq!(err.span, 11..26);
+                assert_eq!(&text[err.span.clone()], "!!!not-valid!!!");
+            }
+            other => panic!("expected a Pep508 error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn pep508_error_reports_line_and_span_for_a_continued_entry() {
+        let text = "flask==2.0\n!!!not \\\n    valid!!!\nattrs==1.0\n";
+
+        match Requirements::from_str(text).unwrap_err() {
+            crate::RequirementsError::Pep508(err) => {
+                // The entry starts on the logical line following the first (valid) requirement,
+                // and its span covers both physical lines, continuation marker included.
+                assert_eq!(err.line, 2);
+                assert_eq!(err.span, 11..32);
+                assert_eq!(&text[err.span.clone()], "!!!not \\\n    valid!!!");
+            }
+            other => panic!("expected a Pep508 error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn strips_inline_trailing_comment() -> Result<()> {
+        let parsed = Requirements::from_str("flask==2.0  # pinned for compat")?;
+
+        assert_eq!(parsed.requirements().len(), 1);
+        assert_eq!(&*parsed.requirements()[0].requirement.name, "flask");
+
+        Ok(())
+    }
+
     #[test]
     fn simple() -> Result<()> {
-        assert_debug_snapshot!(Requirements::from_str(r#"flask==2.0"#)?);
+        let parsed = Requirements::from_str("flask==2.0")?;
+
+        assert_eq!(parsed.requirements().len(), 1);
+        assert_eq!(&*parsed.requirements()[0].requirement.name, "flask");
+        assert!(parsed.requirements()[0].hashes.is_empty());
+        assert!(parsed.editables().is_empty());
+        assert!(parsed.options().is_empty());
+
         Ok(())
     }
 
     #[test]
     fn pip_compile() -> Result<()> {
-        assert_debug_snapshot!(Requirements::from_str(
+        let parsed = Requirements::from_str(
             r#"
 #
 # This file is autogenerated by pip-compile with Python 3.7
@@ -284,8 +953,32 @@ zipp==3.15.0 \
     --hash=sha256:112929ad649da941c23de50f356a2b5570c954b65150642bccdd66bf194d224b \
     --hash=sha256:48904fc76a60e542af151aded95726c1a5c34ed43ab4134b597665c86d7ad556
     # via importlib-metadata
-"#
-        )?);
+"#,
+        )?;
+
+        assert_eq!(parsed.requirements().len(), 11);
+        assert!(parsed.editables().is_empty());
+        assert!(parsed.options().is_empty());
+
+        let attrs = parsed
+            .requirements()
+            .iter()
+            .find(|entry| &*entry.requirement.name == "attrs")
+            .expect("attrs requirement");
+        assert_eq!(attrs.hashes.len(), 2);
+        assert_eq!(attrs.hashes[0].algorithm, HashAlgorithm::Sha256);
+        assert_eq!(
+            attrs.hashes[0].digest,
+            "1f28b4522cdc2fb4256ac1a020c78acf9cba2c6b461ccd2c126f3aa8e8335d04"
+        );
+
+        let ruff = parsed
+            .requirements()
+            .iter()
+            .find(|entry| &*entry.requirement.name == "ruff")
+            .expect("ruff requirement");
+        assert_eq!(ruff.hashes.len(), 17);
+
         Ok(())
     }
 }
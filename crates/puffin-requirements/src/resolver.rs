@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    strip_flag, EditableRequirement, GlobalOption, RequirementEntry, RequirementsEntry,
+    RequirementsError, RequirementsIterator,
+};
+
+/// The flattened result of recursively resolving a `requirements.txt` file and any `-r`/`-c`
+/// includes it references.
+#[derive(Debug, Default)]
+pub struct ResolvedRequirements {
+    pub requirements: Vec<RequirementEntry>,
+    pub constraints: Vec<RequirementEntry>,
+    pub editables: Vec<EditableRequirement>,
+    pub options: Vec<GlobalOption>,
+}
+
+/// An error resolving a `requirements.txt` file and the includes it references.
+#[derive(Debug)]
+pub enum ResolveError {
+    /// Failed to read an included file.
+    Io(PathBuf, std::io::Error),
+    /// Failed to parse a requirement (or `--hash` entry) within a file.
+    Parse(PathBuf, RequirementsError),
+    /// A `-e`/`--editable` or direct URL entry was found in a file included via `-c`/
+    /// `--constraint`, where pip only permits version pins.
+    EditableInConstraints(PathBuf),
+    /// A global option (e.g. `--index-url`, `--pre`) was found in a file included via `-c`/
+    /// `--constraint`, where pip only permits version pins.
+    GlobalOptionInConstraints(PathBuf),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "failed to read `{}`: {err}", path.display()),
+            Self::Parse(path, err) => write!(f, "failed to parse `{}`: {err}", path.display()),
+            Self::EditableInConstraints(path) => write!(
+                f,
+                "`{}` is a constraints file, but contains an editable or URL requirement \
+                 (constraints files may only contain version pins)",
+                path.display()
+            ),
+            Self::GlobalOptionInConstraints(path) => write!(
+                f,
+                "`{}` is a constraints file, but contains a global option \
+                 (constraints files may only contain version pins)",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// Recursively resolve the `requirements.txt` file at `path`, following any `-r`/`--requirement`
+/// and `-c`/`--constraint` includes it references (with paths resolved relative to the including
+/// file), and returning the flattened requirements and constraints.
+pub fn resolve(path: &Path) -> Result<ResolvedRequirements, ResolveError> {
+    let mut resolved = ResolvedRequirements::default();
+    let mut visited = HashSet::new();
+    resolve_into(path, false, &mut visited, &mut resolved)?;
+    Ok(resolved)
+}
+
+/// Resolve `path` into `resolved`, recursing into any `-r`/`-c` includes it references.
+///
+/// `visited` tracks the `(canonicalized path, as_constraint)` pairs we've already read, so that a
+/// cycle of includes terminates rather than recursing forever. Keying on the role as well as the
+/// path lets the same file legitimately contribute to both `requirements` and `constraints` when
+/// it's included once each way (e.g. a shared pins file that's both `-r`'d and `-c`'d).
+fn resolve_into(
+    path: &Path,
+    as_constraint: bool,
+    visited: &mut HashSet<(PathBuf, bool)>,
+    resolved: &mut ResolvedRequirements,
+) -> Result<(), ResolveError> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|err| ResolveError::Io(path.to_path_buf(), err))?;
+    if !visited.insert((canonical, as_constraint)) {
+        return Ok(());
+    }
+
+    let text = fs::read_to_string(path).map_err(|err| ResolveError::Io(path.to_path_buf(), err))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    for line in RequirementsIterator::new(&text) {
+        let line = line.map_err(|err| ResolveError::Parse(path.to_path_buf(), err))?;
+        let text = line.as_str().trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        if let Some(include) = strip_flag(text, &["-r", "--requirement"]) {
+            resolve_into(&dir.join(include), as_constraint, visited, resolved)?;
+            continue;
+        }
+
+        if let Some(include) = strip_flag(text, &["-c", "--constraint"]) {
+            resolve_into(&dir.join(include), true, visited, resolved)?;
+            continue;
+        }
+
+        match RequirementsEntry::parse(line)
+            .map_err(|err| ResolveError::Parse(path.to_path_buf(), err))?
+        {
+            RequirementsEntry::Requirement(entry) => {
+                if as_constraint {
+                    resolved.constraints.push(entry);
+                } else {
+                    resolved.requirements.push(entry);
+                }
+            }
+            RequirementsEntry::Editable(_) if as_constraint => {
+                return Err(ResolveError::EditableInConstraints(path.to_path_buf()));
+            }
+            RequirementsEntry::Editable(entry) => resolved.editables.push(entry),
+            RequirementsEntry::GlobalOption(_) if as_constraint => {
+                return Err(ResolveError::GlobalOptionInConstraints(path.to_path_buf()));
+            }
+            RequirementsEntry::GlobalOption(option) => resolved.options.push(option),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{resolve, ResolveError};
+
+    /// A scratch directory under the OS temp dir, removed (along with its contents) on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "puffin-requirements-resolver-test-{name}-{}",
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, name: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn same_file_can_feed_both_requirements_and_constraints() {
+        let dir = TempDir::new("merge");
+        dir.write("shared.txt", "shared-pkg==1.0\n");
+        let root = dir.write("root.txt", "-r shared.txt\n-c shared.txt\nother-pkg==2.0\n");
+
+        let resolved = resolve(&root).unwrap();
+
+        assert_eq!(resolved.requirements.len(), 2);
+        assert_eq!(resolved.constraints.len(), 1);
+        assert!(resolved
+            .requirements
+            .iter()
+            .any(|entry| &*entry.requirement.name == "shared-pkg"));
+        assert!(resolved
+            .constraints
+            .iter()
+            .any(|entry| &*entry.requirement.name == "shared-pkg"));
+    }
+
+    #[test]
+    fn include_cycle_terminates() {
+        let dir = TempDir::new("cycle");
+        dir.write("a.txt", "-r b.txt\npkg-a==1.0\n");
+        dir.write("b.txt", "-r a.txt\npkg-b==1.0\n");
+        let root = dir.write("root.txt", "-r a.txt\n");
+
+        let resolved = resolve(&root).unwrap();
+
+        assert_eq!(resolved.requirements.len(), 2);
+    }
+
+    #[test]
+    fn editable_in_constraints_file_is_rejected() {
+        let dir = TempDir::new("editable-constraint");
+        dir.write("constraints.txt", "-e file:///tmp/some-pkg\n");
+        let root = dir.write("root.txt", "-c constraints.txt\n");
+
+        let err = resolve(&root).unwrap_err();
+        assert!(matches!(err, ResolveError::EditableInConstraints(_)));
+    }
+
+    #[test]
+    fn global_option_in_constraints_file_is_rejected() {
+        let dir = TempDir::new("option-constraint");
+        dir.write(
+            "constraints.txt",
+            "--index-url https://example.com/simple\n",
+        );
+        let root = dir.write("root.txt", "-c constraints.txt\n");
+
+        let err = resolve(&root).unwrap_err();
+        assert!(matches!(err, ResolveError::GlobalOptionInConstraints(_)));
+    }
+}